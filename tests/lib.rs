@@ -0,0 +1,797 @@
+#![allow(clippy::redundant_field_names)]
+
+use scrypto_test::prelude::*;
+use std::sync::OnceLock;
+
+// Terms passed to issuer_create_instrument, encoded as the InstrumentTerms tuple of the blueprint
+#[derive(ManifestSbor, Clone)]
+struct InstrumentTerms {
+    security_type: String,
+    security_form: String,
+    name: String,
+    symbol: String,
+    isin: String,
+    currency: ResourceAddress,
+    nominal: Decimal,
+    denomination: Decimal,
+    offer_size: Decimal,
+    offer_price: Decimal,
+    issue_date: Instant,
+    maturity_date: Option<Instant>,
+    redemption_price: Decimal,
+    coupon_rate: Decimal,
+    day_count: String,
+    coupon_frequency: u32,
+}
+
+impl InstrumentTerms {
+    // 5% annual bond settling in XRD, issued at par with a nominal of 1 as in 04_issuer_create_instrument.rtm
+    fn bond(security_form: &str) -> Self {
+        Self {
+            security_type: "Bond".to_string(),
+            security_form: security_form.to_string(),
+            name: "Bond1".to_string(),
+            symbol: "BON1".to_string(),
+            isin: "XS0000000001".to_string(),
+            currency: XRD,
+            nominal: dec!(1),
+            denomination: dec!(100),
+            offer_size: dec!(1000000),
+            offer_price: dec!(100),
+            issue_date: Instant::new(1735689600),
+            maturity_date: Some(Instant::new(1893456000)),
+            redemption_price: dec!(100),
+            coupon_rate: dec!(5),
+            day_count: "30/360".to_string(),
+            coupon_frequency: 1,
+        }
+    }
+}
+
+// Issuer badge data minted by the owner, as in 02_owner_mint_issuer_badge.rtm
+#[derive(ManifestSbor)]
+struct IssuerBadge {
+    company_lei: String,
+}
+
+// An account on the ledger holding the badge it signs in with
+struct Party {
+    public_key: Secp256k1PublicKey,
+    account: ComponentAddress,
+    badge: ResourceAddress,
+    badge_id: NonFungibleLocalId,
+}
+
+impl Party {
+    fn signature(&self) -> Vec<NonFungibleGlobalId> {
+        vec![NonFungibleGlobalId::from_public_key(&self.public_key)]
+    }
+}
+
+struct TestEnv {
+    ledger: DefaultLedgerSimulator,
+    component: ComponentAddress,
+    owner: Party,
+    issuer_badge: ResourceAddress,
+    agent_badge: ResourceAddress,
+    investor_badge: ResourceAddress,
+    subscription_badge: ResourceAddress,
+    roll_receipt: ResourceAddress,
+    investor_version: u64,
+    subscription_version: u64,
+    securities: HashMap<(ResourceAddress, u64), ResourceAddress>, // mapping of the instrument and lifecycle version to the security issued for it
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        // the package is compiled once and published to the ledger of every test
+        static PACKAGE: OnceLock<(Vec<u8>, PackageDefinition)> = OnceLock::new();
+        let package = PACKAGE
+            .get_or_init(|| Compile::compile(this_package!(), CompileProfile::Fast))
+            .clone();
+
+        // a genesis validator lets tests advance the ledger clock
+        let mut ledger = LedgerSimulatorBuilder::new()
+            .with_custom_genesis(CustomGenesis::default(Epoch::of(1), CustomGenesis::default_consensus_manager_config()))
+            .build();
+        let package_address = ledger.publish_package(package, BTreeMap::new(), OwnerRole::None);
+        let (public_key, _, account) = ledger.new_allocated_account();
+
+        let receipt = ledger.execute_manifest(
+            ManifestBuilder::new()
+                .lock_fee_from_faucet()
+                .call_function(package_address, "SecuritiesManager", "instantiate_securities_manager", manifest_args!())
+                .deposit_batch(account)
+                .build(),
+            vec![NonFungibleGlobalId::from_public_key(&public_key)],
+        );
+        let commit = receipt.expect_commit_success();
+        let component = commit.new_component_addresses()[0];
+        let resources = commit.new_resource_addresses().clone();
+        let mut resource_named = |name: &str| -> ResourceAddress {
+            *resources
+                .iter()
+                .find(|resource| ledger.get_metadata((**resource).into(), "name") == Some(MetadataValue::String(name.to_string())))
+                .unwrap_or_else(|| panic!("{} not created", name))
+        };
+        let owner_badge = resource_named("Securities Manager Owner Badge");
+        let issuer_badge = resource_named("Issuer Badge");
+        let agent_badge = resource_named("Agent Badge");
+        let investor_badge = resource_named("Investor KYC Badge");
+        let subscription_badge = resource_named("Subscription Badge");
+        let roll_receipt = resource_named("Collateral Roll Receipt");
+
+        Self {
+            ledger: ledger,
+            component: component,
+            owner: Party {
+                public_key: public_key,
+                account: account,
+                badge: owner_badge,
+                badge_id: NonFungibleLocalId::integer(0),
+            },
+            issuer_badge: issuer_badge,
+            agent_badge: agent_badge,
+            investor_badge: investor_badge,
+            subscription_badge: subscription_badge,
+            roll_receipt: roll_receipt,
+            investor_version: 0,
+            subscription_version: 0,
+            securities: HashMap::new(),
+        }
+    }
+
+    fn new_account(&mut self) -> (Secp256k1PublicKey, ComponentAddress) {
+        let (public_key, _, account) = self.ledger.new_allocated_account();
+        (public_key, account)
+    }
+
+    // calls a method of the component passing in a proof of the party's badge, returned resources are deposited in the party's account
+    fn call<A: ResolvableArguments>(&mut self, party: &Party, method: &str, args: impl FnOnce(ManifestProof) -> A) -> TransactionReceipt {
+        self.call_and_deposit(party, party.account, method, args)
+    }
+
+    // as call, depositing the returned resources in another account e.g. a soulbound badge minted for the account
+    fn call_and_deposit<A: ResolvableArguments>(&mut self, party: &Party, account: ComponentAddress, method: &str,
+        args: impl FnOnce(ManifestProof) -> A) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungibles(party.account, party.badge, [party.badge_id.clone()])
+            .create_proof_from_account_of_non_fungibles(party.account, party.badge, [party.badge_id.clone()])
+            .pop_from_auth_zone("badge_proof")
+            .call_method_with_name_lookup(self.component, method, |lookup| args(lookup.proof("badge_proof")))
+            .try_deposit_entire_worktop_or_abort(account, None)
+            .build();
+        self.ledger.execute_manifest(manifest, party.signature())
+    }
+
+    // calls a read only method of the component and returns its output
+    fn get<T: ScryptoDecode>(&mut self, method: &str, args: impl ResolvableArguments) -> T {
+        self.ledger
+            .call_method(self.component, method, args)
+            .expect_commit_success()
+            .output(1)
+    }
+
+    fn onboard_issuer(&mut self, local_id: u64) -> Party {
+        let (public_key, account) = self.new_account();
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_amount(self.owner.account, self.owner.badge, 1)
+            .mint_non_fungible(
+                self.issuer_badge,
+                [(NonFungibleLocalId::integer(local_id), IssuerBadge { company_lei: format!("LEI_ISSUER{}", local_id) })],
+            )
+            .try_deposit_entire_worktop_or_abort(account, None)
+            .build();
+        self.ledger
+            .execute_manifest(manifest, self.owner.signature())
+            .expect_commit_success();
+        Party {
+            public_key: public_key,
+            account: account,
+            badge: self.issuer_badge,
+            badge_id: NonFungibleLocalId::integer(local_id),
+        }
+    }
+
+    fn appoint_agent(&mut self, issuer: &Party, local_id: u64) -> Party {
+        let (public_key, account) = self.new_account();
+        self.call_and_deposit(issuer, account, "issuer_mint_agent_badge", |proof| {
+            (proof, format!("LEI_AGENT{}", local_id), local_id, Vec::<ResourceAddress>::new())
+        })
+        .expect_commit_success();
+        Party {
+            public_key: public_key,
+            account: account,
+            badge: self.agent_badge,
+            badge_id: NonFungibleLocalId::integer(local_id),
+        }
+    }
+
+    // the investor applies for KYC signed by the account owner and the issuer approves the application
+    fn onboard_investor(&mut self, issuer: &Party) -> Party {
+        let (public_key, account) = self.new_account();
+        let application_id: u64 = self
+            .ledger
+            .execute_manifest(
+                ManifestBuilder::new()
+                    .lock_fee_from_faucet()
+                    .call_method(self.component, "investor_apply_kyc", manifest_args!(account, "DE", "Professional"))
+                    .build(),
+                vec![NonFungibleGlobalId::from_public_key(&public_key)],
+            )
+            .expect_commit_success()
+            .output(1);
+        self.call(issuer, "kyc_approve_investor", |proof| (proof, application_id, Instant::new(1893456000)))
+            .expect_commit_success();
+        self.investor_version += 1;
+        Party {
+            public_key: public_key,
+            account: account,
+            badge: self.investor_badge,
+            badge_id: NonFungibleLocalId::integer(self.investor_version),
+        }
+    }
+
+    fn create_instrument(&mut self, issuer: &Party, terms: InstrumentTerms) -> ResourceAddress {
+        self.call(issuer, "issuer_create_instrument", |proof| (proof, terms))
+            .expect_commit_success()
+            .new_resource_addresses()[0]
+    }
+
+    fn open_subscription(&mut self, issuer: &Party, instrument: ResourceAddress, book_building: bool) -> TransactionReceipt {
+        self.call(issuer, "issuer_open_subscription", |proof| {
+            (proof, instrument, book_building, dec!(0), Instant::new(0), Instant::new(4102444800))
+        })
+    }
+
+    fn close_subscription(&mut self, issuer: &Party, instrument: ResourceAddress) -> TransactionReceipt {
+        self.call(issuer, "issuer_close_subscription", |proof| {
+            (proof, instrument, "ProRata", HashMap::<NonFungibleLocalId, u32>::new())
+        })
+    }
+
+    // subscribes for a quantity and transfers the payment, returns the local id of the subscription nft
+    fn subscribe(&mut self, investor: &Party, instrument: ResourceAddress, quantity: Decimal, payment: Decimal) -> NonFungibleLocalId {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .pop_from_auth_zone("investor_proof")
+            .call_method_with_name_lookup(self.component, "investor_subscribe", |lookup| {
+                (lookup.proof("investor_proof"), instrument, quantity)
+            })
+            .take_all_from_worktop(self.subscription_badge, "subscription")
+            .create_proof_from_bucket_of_all("subscription", "subscription_proof")
+            .withdraw_from_account(investor.account, XRD, payment)
+            .take_all_from_worktop(XRD, "payment")
+            .call_method_with_name_lookup(self.component, "investor_transfer_payment", |lookup| {
+                (lookup.proof("subscription_proof"), lookup.bucket("payment"))
+            })
+            .return_to_worktop("subscription")
+            .try_deposit_entire_worktop_or_abort(investor.account, None)
+            .build();
+        self.ledger
+            .execute_manifest(manifest, investor.signature())
+            .expect_commit_success();
+        self.subscription_version += 1;
+        NonFungibleLocalId::integer(self.subscription_version)
+    }
+
+    fn claim_security(&mut self, investor: &Party, subscription: NonFungibleLocalId) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .pop_from_auth_zone("investor_proof")
+            .withdraw_non_fungibles_from_account(investor.account, self.subscription_badge, [subscription.clone()])
+            .take_non_fungibles_from_worktop(self.subscription_badge, [subscription], "subscription")
+            .call_method_with_name_lookup(self.component, "investor_claim_security", |lookup| {
+                (lookup.proof("investor_proof"), lookup.bucket("subscription"))
+            })
+            .try_deposit_entire_worktop_or_abort(investor.account, None)
+            .build();
+        self.ledger.execute_manifest(manifest, investor.signature())
+    }
+
+    fn reclaim_payment(&mut self, investor: &Party, subscription: NonFungibleLocalId) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .withdraw_non_fungibles_from_account(investor.account, self.subscription_badge, [subscription.clone()])
+            .take_non_fungibles_from_worktop(self.subscription_badge, [subscription], "subscription")
+            .call_method_with_name_lookup(self.component, "investor_reclaim_payment", |lookup| (lookup.bucket("subscription"),))
+            .try_deposit_entire_worktop_or_abort(investor.account, None)
+            .build();
+        self.ledger.execute_manifest(manifest, investor.signature())
+    }
+
+    fn add_lifecycle(&mut self, agent: &Party, instrument: ResourceAddress, action_type: &str, percent: Decimal,
+        claim_deadline: Option<Instant>) -> TransactionReceipt {
+        self.call(agent, "agent_add_instrument_lifecycle", |proof| {
+            (proof, instrument, action_type, percent, claim_deadline)
+        })
+    }
+
+    // issues the outstanding security versions and records them by the local id of their lifecycle event
+    fn issue_securities(&mut self, agent: &Party, instrument: ResourceAddress) {
+        let resources = self
+            .call(agent, "agent_issue_lifecycle_securities", |proof| (proof, instrument))
+            .expect_commit_success()
+            .new_resource_addresses()
+            .clone();
+        for security in resources {
+            if let Some(MetadataValue::NonFungibleLocalId(NonFungibleLocalId::Integer(local_id))) =
+                self.ledger.get_metadata(security.into(), "instrument_local_id")
+            {
+                self.securities.insert((instrument, local_id.value()), security);
+            }
+        }
+    }
+
+    fn security(&self, instrument: ResourceAddress, version: u64) -> ResourceAddress {
+        *self
+            .securities
+            .get(&(instrument, version))
+            .expect("Security version not issued")
+    }
+
+    // presents the investor's security version to a claim method e.g. investor_claim_corporate_action
+    fn present_securities(&mut self, investor: &Party, method: &str, security: ResourceAddress, amount: Decimal) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .pop_from_auth_zone("investor_proof")
+            .withdraw_from_account(investor.account, security, amount)
+            .take_all_from_worktop(security, "securities")
+            .call_method_with_name_lookup(self.component, method, |lookup| {
+                (lookup.proof("investor_proof"), lookup.bucket("securities"))
+            })
+            .try_deposit_entire_worktop_or_abort(investor.account, None)
+            .build();
+        self.ledger.execute_manifest(manifest, investor.signature())
+    }
+
+    // deposits the investor's security version into its collateral pool and returns the pool token, created on the first deposit
+    fn deposit_collateral(&mut self, investor: &Party, security: ResourceAddress, amount: Decimal) -> ResourceAddress {
+        self.present_securities(investor, "deposit_collateral", security, amount)
+            .expect_commit_success()
+            .new_resource_addresses()[0]
+    }
+
+    // rolls the investor's collateral position in a security version through the coupon claim back into the pool, as in 22_investor_roll_collateral_coupon.rtm
+    fn roll_collateral(&mut self, investor: &Party, security: ResourceAddress, next_security: ResourceAddress) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .pop_from_auth_zone("roll_proof")
+            .create_proof_from_account_of_non_fungibles(investor.account, investor.badge, [investor.badge_id.clone()])
+            .pop_from_auth_zone("claim_proof")
+            .call_method_with_name_lookup(self.component, "investor_start_collateral_roll", |lookup| {
+                (lookup.proof("roll_proof"), security)
+            })
+            .take_all_from_worktop(security, "securities")
+            .take_all_from_worktop(self.roll_receipt, "roll_receipt")
+            .call_method_with_name_lookup(self.component, "investor_claim_corporate_action", |lookup| {
+                (lookup.proof("claim_proof"), lookup.bucket("securities"))
+            })
+            .take_all_from_worktop(next_security, "next_securities")
+            .call_method_with_name_lookup(self.component, "investor_end_collateral_roll", |lookup| {
+                (lookup.bucket("roll_receipt"), lookup.bucket("next_securities"))
+            })
+            .try_deposit_entire_worktop_or_abort(investor.account, None)
+            .build();
+        self.ledger.execute_manifest(manifest, investor.signature())
+    }
+
+    fn deposit_funds(&mut self, issuer: &Party, instrument: ResourceAddress, amount: Decimal) {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungibles(issuer.account, issuer.badge, [issuer.badge_id.clone()])
+            .create_proof_from_account_of_non_fungibles(issuer.account, issuer.badge, [issuer.badge_id.clone()])
+            .pop_from_auth_zone("issuer_proof")
+            .withdraw_from_account(issuer.account, XRD, amount)
+            .take_all_from_worktop(XRD, "cash")
+            .call_method_with_name_lookup(self.component, "issuer_deposit_funds", |lookup| {
+                (lookup.proof("issuer_proof"), instrument, lookup.bucket("cash"))
+            })
+            .build();
+        self.ledger
+            .execute_manifest(manifest, issuer.signature())
+            .expect_commit_success();
+    }
+
+    fn claim_cash(&mut self, issuer: &Party, instrument: ResourceAddress) -> TransactionReceipt {
+        self.call(issuer, "issuer_claim_cash", |proof| (proof, instrument))
+    }
+
+    // moves the ledger clock forward by a number of seconds
+    fn advance_time(&mut self, seconds: i64) {
+        let round = Round::of(self.ledger.get_consensus_manager_state().round.number() + 1);
+        let timestamp_ms = self.ledger.get_current_proposer_timestamp_ms() + seconds * 1000;
+        self.ledger
+            .advance_to_round_at_timestamp(round, timestamp_ms)
+            .expect_commit_success();
+    }
+
+    fn cash_balance(&mut self, instrument: ResourceAddress) -> Decimal {
+        self.get("get_cash_balance", manifest_args!(instrument))
+    }
+
+    fn balance(&mut self, account: ComponentAddress, resource: ResourceAddress) -> Decimal {
+        self.ledger.get_component_balance(account, resource)
+    }
+
+    // creates a bond with the given issuer and agent, subscribes the investors for their quantities and closes the subscription
+    // the securities of the issuance are issued, but not yet claimed by the investors
+    fn issue_bond(&mut self, issuer: &Party, agent: &Party, security_form: &str, orders: &[(&Party, Decimal)])
+        -> (ResourceAddress, Vec<NonFungibleLocalId>) {
+        let instrument = self.create_instrument(issuer, InstrumentTerms::bond(security_form));
+        self.open_subscription(issuer, instrument, false).expect_commit_success();
+        let subscriptions: Vec<NonFungibleLocalId> = orders
+            .iter()
+            .map(|(investor, quantity)| self.subscribe(investor, instrument, *quantity, *quantity))
+            .collect();
+        self.close_subscription(issuer, instrument).expect_commit_success();
+        self.add_lifecycle(agent, instrument, "Issuance", dec!(100), None)
+            .expect_commit_success();
+        self.issue_securities(agent, instrument);
+        (instrument, subscriptions)
+    }
+}
+
+fn expect_panic(receipt: &TransactionReceipt, message: &str) {
+    receipt.expect_specific_failure(|error| format!("{:?}", error).contains(message));
+}
+
+#[test]
+fn issuer_methods_are_scoped_to_the_instrument_issuer() {
+    let mut env = TestEnv::new();
+    let issuer = env.onboard_issuer(1);
+    let other_issuer = env.onboard_issuer(2);
+    let other_agent = env.appoint_agent(&other_issuer, 2);
+    let investor = env.onboard_investor(&issuer);
+    let instrument = env.create_instrument(&issuer, InstrumentTerms::bond("Bearer"));
+
+    // another onboarded issuer can neither open the subscription nor update the instrument
+    let receipt = env.open_subscription(&other_issuer, instrument, false);
+    expect_panic(&receipt, "Issuer is not owner of this instrument");
+    let receipt = env.call(&other_issuer, "issuer_update_instrument_metadata", |proof| {
+        (proof, instrument, "description", "Not my bond")
+    });
+    expect_panic(&receipt, "Issuer is not owner of this instrument");
+
+    env.open_subscription(&issuer, instrument, false).expect_commit_success();
+    env.subscribe(&investor, instrument, dec!(100), dec!(100));
+
+    // nor close the book or sweep its proceeds
+    let receipt = env.close_subscription(&other_issuer, instrument);
+    expect_panic(&receipt, "Issuer is not owner of this instrument");
+    env.close_subscription(&issuer, instrument).expect_commit_success();
+
+    // an agent appointed by another issuer cannot service the instrument
+    let receipt = env.add_lifecycle(&other_agent, instrument, "Issuance", dec!(100), None);
+    expect_panic(&receipt, "Issuer Agent is not appointed by the issuer of this instrument");
+
+    let receipt = env.claim_cash(&other_issuer, instrument);
+    expect_panic(&receipt, "Issuer is not owner of this instrument");
+    assert_eq!(env.cash_balance(instrument), dec!(100));
+}
+
+#[test]
+fn issuer_methods_require_an_issuer_badge() {
+    let mut env = TestEnv::new();
+    let issuer = env.onboard_issuer(1);
+    let investor = env.onboard_investor(&issuer);
+    let instrument = env.create_instrument(&issuer, InstrumentTerms::bond("Bearer"));
+
+    // an investor badge does not satisfy the issuer role
+    let receipt = env.call(&investor, "issuer_open_subscription", |proof| {
+        (proof, instrument, false, dec!(0), Instant::new(0), Instant::new(4102444800))
+    });
+    receipt.expect_auth_failure();
+    let receipt = env.call(&investor, "issuer_claim_cash", |proof| (proof, instrument));
+    receipt.expect_auth_failure();
+}
+
+#[test]
+fn coupon_cash_is_reserved_for_every_holder_when_the_coupon_is_added() {
+    let mut env = TestEnv::new();
+    let issuer = env.onboard_issuer(1);
+    let agent = env.appoint_agent(&issuer, 1);
+    let investor = env.onboard_investor(&issuer);
+    let other_investor = env.onboard_investor(&issuer);
+    let (instrument, subscriptions) =
+        env.issue_bond(&issuer, &agent, "Bearer", &[(&investor, dec!(100)), (&other_investor, dec!(100))]);
+    env.claim_security(&investor, subscriptions[0].clone()).expect_commit_success();
+    env.claim_security(&other_investor, subscriptions[1].clone()).expect_commit_success();
+    env.claim_cash(&issuer, instrument).expect_commit_success();
+    assert_eq!(env.cash_balance(instrument), dec!(0));
+
+    // the 5% coupon on the outstanding supply of 200 must be pre-funded by the issuer
+    let receipt = env.add_lifecycle(&agent, instrument, "Coupon", dec!(5), None);
+    expect_panic(&receipt, "The issuer has not pre-funded the cash account of the instrument for the lifecycle event");
+    env.deposit_funds(&issuer, instrument, dec!(10));
+    env.add_lifecycle(&agent, instrument, "Coupon", dec!(5), None)
+        .expect_commit_success();
+    env.issue_securities(&agent, instrument);
+    let coupon = NonFungibleGlobalId::new(instrument, NonFungibleLocalId::integer(2));
+    let reserve: Decimal = env.get("get_lifecycle_reserve", manifest_args!(coupon.clone()));
+    assert_eq!(reserve, dec!(10));
+
+    // the reserved cash cannot be withdrawn by the issuer
+    let receipt = env.claim_cash(&issuer, instrument);
+    expect_panic(&receipt, "No funds to withdraw");
+
+    let (v1, v2) = (env.security(instrument, 1), env.security(instrument, 2));
+    for holder in [&investor, &other_investor] {
+        let cash = env.balance(holder.account, XRD);
+        env.present_securities(holder, "investor_claim_corporate_action", v1, dec!(100))
+            .expect_commit_success();
+        assert_eq!(env.balance(holder.account, XRD), cash + dec!(5));
+        assert_eq!(env.balance(holder.account, v2), dec!(100));
+    }
+    let reserve: Decimal = env.get("get_lifecycle_reserve", manifest_args!(coupon));
+    assert_eq!(reserve, dec!(0));
+    assert_eq!(env.cash_balance(instrument), dec!(0));
+}
+
+#[test]
+fn cancelled_offering_holds_the_payments_for_refund_and_releases_the_issuer_funds() {
+    let mut env = TestEnv::new();
+    let issuer = env.onboard_issuer(1);
+    let investor = env.onboard_investor(&issuer);
+    let other_investor = env.onboard_investor(&issuer);
+    let instrument = env.create_instrument(&issuer, InstrumentTerms { offer_size: dec!(200), ..InstrumentTerms::bond("Bearer") });
+
+    // the book is oversubscribed twice and allocated pro-rata, half of each payment is reserved for refund
+    env.open_subscription(&issuer, instrument, true).expect_commit_success();
+    let subscription = env.subscribe(&investor, instrument, dec!(200), dec!(200));
+    let other_subscription = env.subscribe(&other_investor, instrument, dec!(200), dec!(200));
+    env.deposit_funds(&issuer, instrument, dec!(30));
+    env.close_subscription(&issuer, instrument).expect_commit_success();
+    assert_eq!(env.cash_balance(instrument), dec!(430));
+
+    // on cancellation the allocated payments join the refunds and only the issuer's own funds are released
+    env.call(&issuer, "issuer_cancel_offering", |proof| (proof, instrument))
+        .expect_commit_success();
+    let cash = env.balance(issuer.account, XRD);
+    env.claim_cash(&issuer, instrument).expect_commit_success();
+    assert_eq!(env.balance(issuer.account, XRD), cash + dec!(30));
+    let receipt = env.claim_cash(&issuer, instrument);
+    expect_panic(&receipt, "No funds to withdraw");
+
+    for (holder, subscription) in [(&investor, subscription), (&other_investor, other_subscription)] {
+        let cash = env.balance(holder.account, XRD);
+        env.reclaim_payment(holder, subscription).expect_commit_success();
+        assert_eq!(env.balance(holder.account, XRD), cash + dec!(200));
+    }
+    assert_eq!(env.cash_balance(instrument), dec!(0));
+}
+
+#[test]
+fn kyc_application_requires_the_account_owner_and_one_pending_application() {
+    let mut env = TestEnv::new();
+    let (public_key, account) = env.new_account();
+    let (other_public_key, _) = env.new_account();
+    let apply = |signer: &Secp256k1PublicKey| -> (TransactionManifestV1, Vec<NonFungibleGlobalId>) {
+        (
+            ManifestBuilder::new()
+                .lock_fee_from_faucet()
+                .call_method(env.component, "investor_apply_kyc", manifest_args!(account, "DE", "Professional"))
+                .build(),
+            vec![NonFungibleGlobalId::from_public_key(signer)],
+        )
+    };
+
+    // an application for another account is rejected
+    let (manifest, signature) = apply(&other_public_key);
+    env.ledger
+        .execute_manifest(manifest, signature)
+        .expect_auth_assertion_failure();
+
+    let (manifest, signature) = apply(&public_key);
+    env.ledger
+        .execute_manifest(manifest.clone(), signature.clone())
+        .expect_commit_success();
+    let receipt = env.ledger.execute_manifest(manifest, signature);
+    expect_panic(&receipt, "A KYC application is already pending for this account");
+}
+
+#[test]
+fn holder_skips_an_expired_coupon_onto_the_next_version() {
+    let mut env = TestEnv::new();
+    let issuer = env.onboard_issuer(1);
+    let agent = env.appoint_agent(&issuer, 1);
+    let investor = env.onboard_investor(&issuer);
+    let (instrument, subscriptions) = env.issue_bond(&issuer, &agent, "Bearer", &[(&investor, dec!(100))]);
+    env.claim_security(&investor, subscriptions[0].clone()).expect_commit_success();
+    env.claim_cash(&issuer, instrument).expect_commit_success();
+
+    // the first coupon can be claimed for a day, the second has no claim deadline
+    let claim_deadline = env.ledger.get_current_time(TimePrecision::Minute).add_days(1).unwrap();
+    env.deposit_funds(&issuer, instrument, dec!(10));
+    env.add_lifecycle(&agent, instrument, "Coupon", dec!(5), Some(claim_deadline))
+        .expect_commit_success();
+    env.add_lifecycle(&agent, instrument, "Coupon", dec!(5), None)
+        .expect_commit_success();
+    env.issue_securities(&agent, instrument);
+    let (v1, v2, v3) = (env.security(instrument, 1), env.security(instrument, 2), env.security(instrument, 3));
+
+    let receipt = env.present_securities(&investor, "investor_skip_expired_lifecycle_event", v1, dec!(100));
+    expect_panic(&receipt, "The lifecycle event has not expired and must be claimed");
+
+    // once expired the unclaimed coupon is released to the issuer and the holder keeps the later coupon
+    env.advance_time(2 * 86400);
+    env.call(&agent, "agent_expire_lifecycle_event", |proof| (proof, instrument, NonFungibleLocalId::integer(2)))
+        .expect_commit_success();
+    let receipt = env.present_securities(&investor, "investor_claim_corporate_action", v1, dec!(100));
+    expect_panic(&receipt, "The lifecycle event is no longer available to be claimed");
+    env.present_securities(&investor, "investor_skip_expired_lifecycle_event", v1, dec!(100))
+        .expect_commit_success();
+    assert_eq!(env.balance(investor.account, v1), dec!(0));
+    assert_eq!(env.balance(investor.account, v2), dec!(100));
+
+    let cash = env.balance(investor.account, XRD);
+    env.present_securities(&investor, "investor_claim_corporate_action", v2, dec!(100))
+        .expect_commit_success();
+    assert_eq!(env.balance(investor.account, XRD), cash + dec!(5));
+    assert_eq!(env.balance(investor.account, v3), dec!(100));
+
+    let cash = env.balance(issuer.account, XRD);
+    env.claim_cash(&issuer, instrument).expect_commit_success();
+    assert_eq!(env.balance(issuer.account, XRD), cash + dec!(5));
+}
+
+#[test]
+fn agent_pays_registered_holders_in_batches() {
+    let mut env = TestEnv::new();
+    let issuer = env.onboard_issuer(1);
+    let agent = env.appoint_agent(&issuer, 1);
+    let holders: Vec<Party> = (0..3).map(|_| env.onboard_investor(&issuer)).collect();
+    let orders: Vec<(&Party, Decimal)> = holders.iter().map(|holder| (holder, dec!(100))).collect();
+    let (instrument, subscriptions) = env.issue_bond(&issuer, &agent, "Registered", &orders);
+    let holder_ids: Vec<NonFungibleGlobalId> = holders
+        .iter()
+        .map(|holder| NonFungibleGlobalId::new(env.investor_badge, holder.badge_id.clone()))
+        .collect();
+
+    // the last subscriber only claims the security once the coupon has been paid to the register
+    for (holder, subscription) in holders.iter().zip(&subscriptions).take(2) {
+        env.claim_security(holder, subscription.clone()).expect_commit_success();
+    }
+    env.claim_cash(&issuer, instrument).expect_commit_success();
+    let register: HashMap<NonFungibleGlobalId, Decimal> = env.get("get_holder_register", manifest_args!(instrument));
+    assert_eq!(register, HashMap::from([(holder_ids[0].clone(), dec!(100)), (holder_ids[1].clone(), dec!(100))]));
+
+    env.deposit_funds(&issuer, instrument, dec!(15));
+    env.add_lifecycle(&agent, instrument, "Coupon", dec!(5), None)
+        .expect_commit_success();
+    let coupon = NonFungibleGlobalId::new(instrument, NonFungibleLocalId::integer(2));
+    let cash: Vec<Decimal> = holders.iter().map(|holder| env.balance(holder.account, XRD)).collect();
+    for _ in 0..2 {
+        env.call(&agent, "agent_pay_registered_holders", |proof| (proof, instrument, NonFungibleLocalId::integer(2), 1u32))
+            .expect_commit_success();
+    }
+    assert_eq!(env.balance(holders[0].account, XRD), cash[0] + dec!(5));
+    assert_eq!(env.balance(holders[1].account, XRD), cash[1] + dec!(5));
+
+    env.claim_security(&holders[2], subscriptions[2].clone()).expect_commit_success();
+    let register: HashMap<NonFungibleGlobalId, Decimal> = env.get("get_holder_register", manifest_args!(instrument));
+    assert_eq!(register.get(&holder_ids[2]), Some(&dec!(100)));
+    let entitlements: HashMap<NonFungibleGlobalId, Decimal> =
+        env.get("get_registered_entitlements", manifest_args!(coupon.clone()));
+    assert_eq!(entitlements, HashMap::from([(holder_ids[2].clone(), dec!(5))]));
+
+    env.call(&agent, "agent_pay_registered_holders", |proof| (proof, instrument, NonFungibleLocalId::integer(2), 1u32))
+        .expect_commit_success();
+    assert_eq!(env.balance(holders[2].account, XRD), cash[2] + dec!(5));
+    let reserve: Decimal = env.get("get_lifecycle_reserve", manifest_args!(coupon));
+    assert_eq!(reserve, dec!(0));
+
+    // only the payment of the last subscriber is left for the issuer
+    let cash = env.balance(issuer.account, XRD);
+    env.claim_cash(&issuer, instrument).expect_commit_success();
+    assert_eq!(env.balance(issuer.account, XRD), cash + dec!(100));
+    assert_eq!(env.cash_balance(instrument), dec!(0));
+}
+
+#[test]
+fn collateral_roll_claims_the_coupon_and_rebacks_the_pool_with_the_next_version() {
+    let mut env = TestEnv::new();
+    let issuer = env.onboard_issuer(1);
+    let agent = env.appoint_agent(&issuer, 1);
+    let investor = env.onboard_investor(&issuer);
+    let (instrument, subscriptions) = env.issue_bond(&issuer, &agent, "Bearer", &[(&investor, dec!(100))]);
+    env.claim_security(&investor, subscriptions[0].clone()).expect_commit_success();
+    env.claim_cash(&issuer, instrument).expect_commit_success();
+    env.deposit_funds(&issuer, instrument, dec!(5));
+    env.add_lifecycle(&agent, instrument, "Coupon", dec!(5), None)
+        .expect_commit_success();
+    env.issue_securities(&agent, instrument);
+    let (v1, v2) = (env.security(instrument, 1), env.security(instrument, 2));
+
+    let pool_token = env.deposit_collateral(&investor, v1, dec!(100));
+    assert_eq!(env.balance(investor.account, pool_token), dec!(100));
+
+    // the roll receipt cannot leave the transaction unless the pool is re-backed
+    env.call(&investor, "investor_start_collateral_roll", |proof| (proof, v1))
+        .expect_auth_failure();
+
+    let cash = env.balance(investor.account, XRD);
+    env.roll_collateral(&investor, v1, v2).expect_commit_success();
+    assert_eq!(env.balance(investor.account, XRD), cash + dec!(5));
+    assert_eq!(env.balance(investor.account, pool_token), dec!(100));
+    let pool: HashMap<ResourceAddress, Decimal> = env.get("get_collateral_pool", manifest_args!("GOVT-AAA+"));
+    assert_eq!(pool.get(&v1).copied().unwrap_or_default(), dec!(0));
+    assert_eq!(pool.get(&v2), Some(&dec!(100)));
+    let positions: HashMap<ResourceAddress, HashMap<NonFungibleGlobalId, Decimal>> =
+        env.get("get_collateral_positions", manifest_args!("GOVT-AAA+"));
+    let investor_id = NonFungibleGlobalId::new(env.investor_badge, investor.badge_id.clone());
+    assert_eq!(positions.get(&v2), Some(&HashMap::from([(investor_id, dec!(100))])));
+
+    // the redemption has no next version to roll into
+    env.deposit_funds(&issuer, instrument, dec!(105));
+    env.add_lifecycle(&agent, instrument, "Redemption", dec!(100), None)
+        .expect_commit_success();
+    let receipt = env.call(&investor, "investor_start_collateral_roll", |proof| (proof, v2));
+    expect_panic(&receipt, "Securities due for redemption cannot be rolled");
+}
+
+#[test]
+fn collateral_positions_stay_with_the_depositor_when_pool_tokens_are_transferred() {
+    let mut env = TestEnv::new();
+    let issuer = env.onboard_issuer(1);
+    let agent = env.appoint_agent(&issuer, 1);
+    let depositor = env.onboard_investor(&issuer);
+    let counterparty = env.onboard_investor(&issuer);
+    let (instrument, subscriptions) = env.issue_bond(&issuer, &agent, "Bearer", &[(&depositor, dec!(100))]);
+    env.claim_security(&depositor, subscriptions[0].clone()).expect_commit_success();
+    env.claim_cash(&issuer, instrument).expect_commit_success();
+    env.deposit_funds(&issuer, instrument, dec!(5));
+    env.add_lifecycle(&agent, instrument, "Coupon", dec!(5), None)
+        .expect_commit_success();
+    env.issue_securities(&agent, instrument);
+    let (v1, v2) = (env.security(instrument, 1), env.security(instrument, 2));
+
+    // the counterparty receives half of the pool tokens and redeems them for the securities
+    let pool_token = env.deposit_collateral(&depositor, v1, dec!(100));
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .withdraw_from_account(depositor.account, pool_token, dec!(50))
+        .try_deposit_entire_worktop_or_abort(counterparty.account, None)
+        .build();
+    env.ledger
+        .execute_manifest(manifest, depositor.signature())
+        .expect_commit_success();
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .withdraw_from_account(counterparty.account, pool_token, dec!(50))
+        .take_all_from_worktop(pool_token, "pool_tokens")
+        .call_method_with_name_lookup(env.component, "redeem_collateral", |lookup| (lookup.bucket("pool_tokens"),))
+        .try_deposit_entire_worktop_or_abort(counterparty.account, None)
+        .build();
+    env.ledger
+        .execute_manifest(manifest, counterparty.signature())
+        .expect_commit_success();
+    assert_eq!(env.balance(counterparty.account, v1), dec!(50));
+
+    // the depositor's position is reduced pro-rata by the redemption and stays with the depositor
+    let positions: HashMap<ResourceAddress, HashMap<NonFungibleGlobalId, Decimal>> =
+        env.get("get_collateral_positions", manifest_args!("GOVT-AAA+"));
+    let depositor_id = NonFungibleGlobalId::new(env.investor_badge, depositor.badge_id.clone());
+    assert_eq!(positions.get(&v1), Some(&HashMap::from([(depositor_id, dec!(50))])));
+
+    // each side is paid the coupon on the securities it is entitled to
+    let cash = env.balance(depositor.account, XRD);
+    env.roll_collateral(&depositor, v1, v2).expect_commit_success();
+    assert_eq!(env.balance(depositor.account, XRD), cash + dec!(2.5));
+    let cash = env.balance(counterparty.account, XRD);
+    env.present_securities(&counterparty, "investor_claim_corporate_action", v1, dec!(50))
+        .expect_commit_success();
+    assert_eq!(env.balance(counterparty.account, XRD), cash + dec!(2.5));
+    assert_eq!(env.balance(counterparty.account, v2), dec!(50));
+}